@@ -0,0 +1,170 @@
+/*
+ * The block.rs file of srap
+ *
+ * Copyright 2024 © max 74.25 <maximillian[at]disroot[dot]org>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but
+ * WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+*/
+
+// the srap-managed section of an rc file, so reruns can tell what they've
+// already appended instead of duplicating it
+const MARKER_NAME: &str = "srap managed";
+
+pub struct ManagedBlock {
+    prefix: String,
+    entries: Vec<String>,
+    suffix: String,
+}
+
+impl ManagedBlock {
+    // find the block between the `>>> srap managed >>>` / `<<< srap managed <<<`
+    // markers in `content`, if one is there
+    pub fn parse(content: &str, comment_char: &str) -> ManagedBlock {
+        let start_marker = format!("{comment_char} >>> {MARKER_NAME} >>>");
+        let end_marker = format!("{comment_char} <<< {MARKER_NAME} <<<");
+
+        let lines: Vec<&str> = content.lines().collect();
+        let start = lines.iter().position(|l| l.trim() == start_marker);
+        let end = lines.iter().position(|l| l.trim() == end_marker);
+
+        if let (Some(s), Some(e)) = (start, end) {
+            if e > s {
+                return ManagedBlock {
+                    prefix: lines[..s].join("\n"),
+                    entries: lines[s + 1..e].iter().map(|l| l.to_string()).collect(),
+                    suffix: lines[e + 1..].join("\n"),
+                };
+            }
+        }
+
+        ManagedBlock {
+            prefix: content.trim_end_matches('\n').to_string(),
+            entries: Vec::new(),
+            suffix: String::new(),
+        }
+    }
+
+    // true if an identical entry is already tracked in the block
+    pub fn contains(&self, entry: &str) -> bool {
+        self.entries.iter().any(|existing| existing == entry)
+    }
+
+    // add `entry` unless it's already present; returns whether it was added
+    pub fn insert(&mut self, entry: String) -> bool {
+        if self.contains(&entry) { return false; }
+        self.entries.push(entry);
+        true
+    }
+
+    // drop `entry` from the block; returns whether anything was removed
+    pub fn remove(&mut self, entry: &str) -> bool {
+        let before = self.entries.len();
+        self.entries.retain(|existing| existing != entry);
+        self.entries.len() != before
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    // render the file content back out, dropping the markers entirely once
+    // the block has no entries left
+    pub fn render(&self, comment_char: &str) -> String {
+        let mut out = self.prefix.clone();
+
+        if !self.is_empty() {
+            if !out.is_empty() { out.push('\n'); }
+            out.push_str(&format!("{comment_char} >>> {MARKER_NAME} >>>\n"));
+            for entry in &self.entries {
+                out.push_str(entry);
+                out.push('\n');
+            }
+            out.push_str(&format!("{comment_char} <<< {MARKER_NAME} <<<"));
+        }
+
+        if !self.suffix.is_empty() {
+            if !out.is_empty() { out.push('\n'); }
+            out.push_str(&self.suffix);
+        }
+
+        out.push('\n');
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn insert_into_empty_file_adds_a_fresh_block() {
+        let mut block = ManagedBlock::parse("", "#");
+        assert!(block.insert("export FOO=bar".to_owned()));
+
+        assert_eq!(
+            block.render("#"),
+            "# >>> srap managed >>>\nexport FOO=bar\n# <<< srap managed <<<\n"
+        );
+    }
+
+    #[test]
+    fn insert_is_idempotent() {
+        let mut block = ManagedBlock::parse("", "#");
+        assert!(block.insert("export FOO=bar".to_owned()));
+        assert!(!block.insert("export FOO=bar".to_owned()));
+
+        assert_eq!(block.entries.len(), 1);
+    }
+
+    #[test]
+    fn reparsing_rendered_output_round_trips_the_entry() {
+        let mut block = ManagedBlock::parse("", "#");
+        block.insert("export FOO=bar".to_owned());
+        let rendered = block.render("#");
+
+        let mut reparsed = ManagedBlock::parse(&rendered, "#");
+        assert!(reparsed.contains("export FOO=bar"));
+        assert!(!reparsed.insert("export FOO=bar".to_owned()));
+    }
+
+    #[test]
+    fn remove_drops_the_entry_and_then_the_whole_block() {
+        let existing = "alias ll='ls -la'\n# >>> srap managed >>>\nexport FOO=bar\n# <<< srap managed <<<\n";
+        let mut block = ManagedBlock::parse(existing, "#");
+
+        assert!(block.remove("export FOO=bar"));
+        assert!(block.is_empty());
+        assert_eq!(block.render("#"), "alias ll='ls -la'\n");
+    }
+
+    #[test]
+    fn remove_of_a_missing_entry_changes_nothing() {
+        let mut block = ManagedBlock::parse("", "#");
+        block.insert("export FOO=bar".to_owned());
+
+        assert!(!block.remove("export BAZ=qux"));
+        assert!(block.contains("export FOO=bar"));
+    }
+
+    #[test]
+    fn parse_preserves_content_outside_the_block() {
+        let existing = "alias ll='ls -la'\n# >>> srap managed >>>\nexport FOO=bar\n# <<< srap managed <<<\nalias gs='git status'\n";
+        let mut block = ManagedBlock::parse(existing, "#");
+        block.insert("export BAZ=qux".to_owned());
+
+        let rendered = block.render("#");
+        assert!(rendered.starts_with("alias ll='ls -la'\n"));
+        assert!(rendered.ends_with("alias gs='git status'\n"));
+    }
+}
@@ -4,192 +4,221 @@
  * Copyright 2024 © max 74.25 <maximillian[at]disroot[dot]org>
  *
  * This program is free software: you can redistribute it and/or modify
- * it under the terms of the GNU General Public License as published by 
- * the Free Software Foundation, either version 3 of the License, or 
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
  * (at your option) any later version.
  *
- * This program is distributed in the hope that it will be useful, but 
- * WITHOUT ANY WARRANTY; without even the implied warranty of 
- * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the 
+ * This program is distributed in the hope that it will be useful, but
+ * WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
  * GNU General Public License for more details.
  *
- * You should have received a copy of the GNU General Public License 
+ * You should have received a copy of the GNU General Public License
  * along with this program. If not, see <https://www.gnu.org/licenses/>.
 */
 
-use std::{ env, fs, path::Path };
-
-// a simple struct to hold the config vars
+use std::{ env, fs, path::Path, process, time::{ SystemTime, UNIX_EPOCH } };
+
+use clap::Parser;
+
+mod block;
+mod shell;
+mod write;
+#[cfg(test)]
+mod testutil;
+use block::ManagedBlock;
+use shell::{ Shell, Posix };
+use write::PlannedWrite;
+
+// the command-line interface, one field per flag; clap fills this in for us
+// instead of us scanning the raw argv by hand
+#[derive(Parser, Debug)]
+#[command(
+    name = "srap",
+    version,
+    about = "srap - the Shell Rc APpender",
+    after_help = "Supports bash, ksh, nsh, zsh as POSIX-compliant, and fish, tcsh, and ion shells \
+(stable for both bash and zsh, everything else experimental)\n\nMade w love by max <3"
+)]
 struct SrapConfig {
+    /// append line to every supported shell's rc file (POSIX, fish, ion, tcsh)
+    #[arg(short = 'a', long = "all")]
     all: bool,
+
+    /// do a dry run of the program
+    #[arg(short = 'd', long = "dry-run")]
     dryrun: bool,
-    file: String,
+
+    /// specify a file
+    #[arg(short = 'f', long = "file")]
+    file: Option<String>,
+
+    /// no colored output
+    #[arg(short = 'n', long = "no-color")]
     nocolor: bool,
-    verbose: bool
+
+    /// don't keep a timestamped .bak copy of each rc file before writing it
+    #[arg(long = "no-backup")]
+    no_backup: bool,
+
+    /// remove a previously appended line, or the whole managed block if it's
+    /// the last one
+    #[arg(short = 'r', long = "remove")]
+    remove: bool,
+
+    /// verbose output
+    #[arg(short = 'v', long = "verbose")]
+    verbose: bool,
+
+    /// the line to append; prefix with `--` if it starts with a `-`
+    #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+    line: Vec<String>,
 }
 
-impl SrapConfig {
-    // init the config with some optional default values
-    fn new_default() -> SrapConfig {
-        SrapConfig { all: false, dryrun: false, file: String::new(), nocolor: false, verbose: false }
+// print the pre-write "Appending/Removing ... to/from ..." line in srap's
+// usual color scheme
+fn announce(config: &SrapConfig, verb: &str, preposition: &str, entry: &str, target: &str) {
+    if config.nocolor {
+        println!("{verb} \"{entry}\" {preposition} {target}");
+    } else {
+        println!("\x1b[35;1m{verb}\x1b[0m \"{entry}\" \x1b[35;1m{preposition}\x1b[0m \x1b[36m{target}\x1b[0m");
     }
 }
 
-// parse the command-line arguments from a String vector and return the config struct
-fn parse_args(args: &mut Vec<String>) -> SrapConfig {
-    let mut config = SrapConfig::new_default();
+// apply an append or removal against a managed block parsed out of
+// `existing_config`, returning the file's new contents if anything changed.
+// Pure and I/O-free so --all can compute every file's outcome before
+// committing any of them to disk.
+fn compute_change(config: &SrapConfig, shell: &dyn Shell, formatted_line: &str, existing_config: &str) -> Option<String> {
+    let mut managed = ManagedBlock::parse(existing_config, shell.comment_char());
 
-    if args.contains(&"-a".to_owned()) || args.contains(&"--all".to_owned()) {
-        config.all = true;
-    }
+    let changed = if config.remove {
+        managed.remove(formatted_line)
+    } else {
+        managed.insert(formatted_line.to_owned())
+    };
 
-    if args.contains(&"-d".to_owned()) || args.contains(&"--dry-run".to_owned()) {
-        config.dryrun = true;
-    }
-    
-    if args.contains(&"-v".to_owned()) || args.contains(&"--verbose".to_owned()) {
-        config.verbose = true;
-    }
-    
-    if args.contains(&"-f".to_owned()) || args.contains(&"--file".to_owned()) {
-        // find the nect argument, this is the filename.
-        let file_arg_index = match args.iter().position( |a| a.contains("-f") ) {
-            None => { panic!("You must provide a filename!"); },
-            Some(index) => index
-        } + 1;
-
-        let filename = args[file_arg_index].clone();
-
-        args.remove(file_arg_index); //gotta remove the file argument, otherwise itll end up
-                                     //messing up the line
-        config.file = filename;
-        
-        if config.verbose { println!("index: {file_arg_index}; filename: {}, args {:?}", &config.file, &args); }
-    }
+    if !changed { return None; }
 
-    if args.contains(&"-n".to_owned()) || args.contains(&"--no-color".to_owned()) {
-        config.nocolor = true;
-    }
-
-    config
+    Some(managed.render(shell.comment_char()))
 }
 
-fn print_help() {
-    println!("srap - the Shell Rc APpender
+// print either the pre-write announcement or a "nothing to do" note for one
+// config file, depending on whether it actually changed
+fn describe_change(config: &SrapConfig, formatted_line: &str, config_file: &str, changed: bool) {
+    if !changed {
+        let reason = if config.remove { "not present, nothing to remove" } else { "already present, skipping" };
+        println!("{config_file}: {reason}");
+        return;
+    }
 
-Usage: srap [options] <line to append>
-Options:
--a / --all             : append line to all POSIX-compliant shells
--d / --dry-run         : do a dry run of the program
--f / --file <filename> : specify a file
--h / --help            : show this help
--n / --no-color        : no colored output
--v / --verbose         : verbose output
+    let (verb, preposition) = if config.remove { ("Removing", "from") } else { ("Appending", "to") };
+    announce(config, verb, preposition, formatted_line, config_file);
+}
 
-Supports bash, ksh, nsh, zsh as POSIX-compliant, and fish, tcsh, and ion shells 
-(stable for both bash and zsh, everything else experimental)
+// report that a candidate rc file doesn't exist; returns true if it was
+// indeed missing, so callers can skip it
+fn report_if_missing(config: &SrapConfig, config_file: &str) -> bool {
+    if Path::new(config_file).is_file() { return false; }
 
-Made w love by max <3");
+    if config.nocolor {
+        println!("{config_file} not found");
+    } else {
+        println!("\x1b[36m{config_file}\x1b[0m \x1b[31;1mnot found\x1b[0m");
+    }
 
+    true
 }
 
-fn main() {
-    let mut args: Vec<String> = env::args().collect();
-    args.remove(0); // discard the first arg, as we dont care where the binary is
-    
-    // show help if there isn't a line, or the arguments to show it are passed, and exit the
-    // program
-    if args.len() == 0 || args.contains(&"-h".to_owned()) || args.contains(&"--help".to_owned()) {
-        print_help();
-        return;
-    } 
-
-    let config = parse_args(&mut args); // get the config
-    
-    if config.verbose { println!("{:?}", &args); }
+// do the actual append, returning an error instead of panicking so main can
+// report it and exit cleanly
+fn run(config: SrapConfig) -> Result<(), String> {
+    if config.verbose { println!("{:?}", &config); }
 
     // notify the user if we are doing a dry run
     if config.dryrun { println!("{}", { if config.nocolor { "Doing a dry run..." } else { "\x1b[31;1mDoing a dry run...\x1b[0m" }}) };
 
-    // get the line index
-    let line = match args.iter().position( |a| !a.starts_with("-") ) {
-        None => { print_help(); return; },
-        Some(index) => index
-    };
-
-    // get the line to append from the index and the length
-    let mut line_to_append: String = if args.len() > 0 { 
-        format!("\n{}", &args[line..args.len()].join(" "))
-    } else {
-        println!("Please enter a line!"); /* getting here shouldn't be possible */"".to_owned()
-    };
-
-    // just a helper thing to add in the correct "" if alias is in the line
-    if line_to_append.contains("alias") && !line_to_append.contains("\"") {
-        let alias_start = match line_to_append.find("=") { Some(val) => val, _ => 1992 } + 1;
-        line_to_append.insert(alias_start, '\"');
-        line_to_append.insert(line_to_append.len(), '\"');
+    if config.line.is_empty() {
+        return Err("Please enter a line!".to_owned());
     }
 
-    if config.verbose { println!("appending line: `{}`", line_to_append); }
+    // the raw line as the user typed it; each Shell translates this into its
+    // own alias/export syntax
+    let raw_line = config.line.join(" ");
 
-    if config.all {
-        // do all the posix compliant shells
-        let mut config_files: Vec<String> = vec!["~/.bashrc".to_string(), 
-                                             "~/.zshrc".to_string(),
-                                             "~/.nshrc".to_string(),
-                                             "~/.kshrc".to_string()];
-        
-        if !config.file.is_empty() { config_files.push(config.file) }
+    // one timestamp for every backup this run makes, so a single --all
+    // invocation produces matching .bak.<timestamp> files
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map_err(|e| e.to_string())?.as_secs();
 
+    if config.all {
         // get the home directory
         let home_dir = match env::var("HOME") {
             Ok(val) => val,
             Err(e) => { println!("Couln't find HOME env var! {e}, continuing..."); "".to_string() },
         };
 
-        for mut config_file in config_files{
-            config_file = config_file.replace("~", home_dir.as_str()); // expand the ~ to the full
-                                                                       // path
+        let xdg_config_home = env::var("XDG_CONFIG_HOME").ok();
+
+        // compute every file's outcome before writing any of them, so a
+        // failure partway through a batch can still be rolled back cleanly
+        let mut planned: Vec<PlannedWrite> = Vec::new();
+
+        for shell in shell::all() {
+            // use whichever of this shell's candidate rc files already
+            // exists, falling back to the most preferred candidate
+            let candidates = shell.rc_candidates(&home_dir, xdg_config_home.as_deref());
+            let config_file = candidates.iter()
+                .find(|path| path.is_file())
+                .unwrap_or(&candidates[0])
+                .to_string_lossy()
+                .into_owned();
 
             if config.verbose { println!("Using presumed config file path: {}", config_file); }
 
-            // check the file exists, continue through the files if it doesn't
-            if config.nocolor {
-                if !Path::new(&config_file).is_file() { println!("{} not found", config_file); continue; } 
-            } else { 
-                if !Path::new(&config_file).is_file() { println!("\x1b[36m{}\x1b[0m \x1b[31;1mnot found\x1b[0m", config_file); continue; } 
-            }
-            
-            // read the existing config
-            let existing_config = match fs::read_to_string(config_file.clone()) {
-                Ok(val) => val,
-                Err(e) => panic!("couldnt read file: {}, {e}", &config_file)
-            };
-
-            if config.nocolor {
-                println!("Appending \"{:}\" to {}", match line_to_append.strip_prefix("\n") { None => "", Some(s) => s } , &config_file);
-            } else {
-                println!("\x1b[35;1mAppending\x1b[0m \"{:}\" \x1b[35;1mto\x1b[0m \x1b[36m{}\x1b[0m", match line_to_append.strip_prefix("\n") { None => "", Some(s) => s } , &config_file);
+            if report_if_missing(&config, &config_file) { continue; }
+
+            let existing_config = fs::read_to_string(&config_file)
+                .map_err(|e| format!("couldnt read file: {config_file}, {e}"))?;
+
+            let formatted_line = shell.format_line(&raw_line);
+
+            if config.verbose { println!("formatted line for {config_file}: `{formatted_line}`"); }
+
+            match compute_change(&config, shell.as_ref(), &formatted_line, &existing_config) {
+                None => describe_change(&config, &formatted_line, &config_file, false),
+                Some(new_config) => {
+                    describe_change(&config, &formatted_line, &config_file, true);
+                    planned.push(PlannedWrite { path: config_file, contents: new_config });
+                }
             }
-            
-            // append the new line
-            let new_config = existing_config + &line_to_append;
-
-            // if it's not a dry run, write to the file the new config
-            if !config.dryrun {
-                match fs::write(&config_file, new_config) {
-                    Ok(result) => result,
-                    Err(e) => panic!("writing file failed! {config_file}: {e}")
+        }
+
+        // an explicit --file isn't tied to any particular shell, so it's
+        // appended in plain POSIX syntax alongside the shells above
+        if let Some(file) = &config.file {
+            if !report_if_missing(&config, file) {
+                let shell = Posix::Bash;
+                let formatted_line = shell.format_line(&raw_line);
+
+                let existing_config = fs::read_to_string(file)
+                    .map_err(|e| format!("couldnt read file: {file}, {e}"))?;
+
+                match compute_change(&config, &shell, &formatted_line, &existing_config) {
+                    None => describe_change(&config, &formatted_line, file, false),
+                    Some(new_config) => {
+                        describe_change(&config, &formatted_line, file, true);
+                        planned.push(PlannedWrite { path: file.clone(), contents: new_config });
+                    }
                 }
             }
         }
+
+        if !config.dryrun && !planned.is_empty() {
+            write::write_batch(&planned, !config.no_backup, timestamp)?;
+        }
     } else {
         // get which shell we're in
-        let shell = match env::var("SHELL") {
-            Ok(val) => val,
-            Err(e) => panic!("couldn't interpret SHELL: {e}"),
-        };
+        let shell_env = env::var("SHELL").map_err(|e| format!("couldn't interpret SHELL: {e}"))?;
 
         // get the home directory
         let home_dir = match env::var("HOME") {
@@ -197,52 +226,48 @@ fn main() {
             Err(e) => { println!("Couln't find HOME env var! {e}, continuing..."); "".to_string() },
         };
 
-        if config.verbose { println!("SHELL: {shell}"); }
+        if config.verbose { println!("SHELL: {shell_env}"); }
+
+        let shell = shell::detect(&shell_env).ok_or_else(|| format!("Unsupported shell!: {shell_env}"))?;
+
+        let xdg_config_home = env::var("XDG_CONFIG_HOME").ok();
 
         // find the config file we're using
-        let config_file_path = {
-            if !config.file.is_empty() {
-                config.file.as_str()
-            } else if shell.as_str().contains("zsh") {
-                "~/.zshrc"
-            } else if shell.as_str() .contains("bash"){
-                "~/.bashrc"
-            } else if shell.as_str().contains("nsh") {
-                "~/.nshrc"
-            } else if shell.as_str().contains("ksh") {
-                "~/.kshrc"
-            } else if shell.as_str().contains("fish") {
-                "~/.config/fish/config.fish"
-            } else if shell.as_str().contains("ion") {
-                ".config/ion/initrc"
-            } else if shell.as_str().contains("tcsh") {
-                "~/.cshrc"
-            } else {
-                panic!("Unsupported shell!: {shell}")
-            }
-        }.replace("~", home_dir.as_str());
+        let config_file_path = match &config.file {
+            Some(file) => file.clone(),
+            None => shell.rc_path(&home_dir, xdg_config_home.as_deref()).to_string_lossy().into_owned(),
+        };
 
         if config.verbose { println!("Using presumed config file path: {}", config_file_path); }
 
-        let existing_config = match fs::read_to_string(config_file_path.clone()) {
-            Ok(val) => val,
-            Err(e) => panic!("couldnt read file: {config_file_path}, {e}")
-        };
+        let formatted_line = shell.format_line(&raw_line);
 
-        if config.nocolor {
-            println!("Appending \"{:}\" to {}", match line_to_append.strip_prefix("\n") { None => "", Some(s) => s } , config_file_path);
-        } else {
-            println!("\x1b[35;1mAppending\x1b[0m \"{:}\" \x1b[35;1mto\x1b[0m \x1b[36m{}\x1b[0m", match line_to_append.strip_prefix("\n") { None => "", Some(s) => s } , config_file_path);
-        }
+        if config.verbose { println!("formatted line: `{}`", formatted_line); }
 
-        let new_config = existing_config + &line_to_append;
+        let existing_config = fs::read_to_string(config_file_path.clone())
+            .map_err(|e| format!("couldnt read file: {config_file_path}, {e}"))?;
 
-        if !config.dryrun {
-            match fs::write(config_file_path, new_config) {
-                Ok(result) => result,
-                Err(e) => panic!("writing file failed! {e}")
+        match compute_change(&config, shell.as_ref(), &formatted_line, &existing_config) {
+            None => describe_change(&config, &formatted_line, &config_file_path, false),
+            Some(new_config) => {
+                describe_change(&config, &formatted_line, &config_file_path, true);
+
+                if !config.dryrun {
+                    write::write_file(&config_file_path, &new_config, !config.no_backup, timestamp)?;
+                }
             }
         }
     }
     println!("{}", if config.nocolor {"Now source the config file and you're all ready to go! :3"} else {"\x1b[32mNow source the config file and you're all ready to go! :3\x1b[0m"});
-} 
+
+    Ok(())
+}
+
+fn main() {
+    let config = SrapConfig::parse();
+
+    if let Err(e) = run(config) {
+        eprintln!("{e}");
+        process::exit(1);
+    }
+}
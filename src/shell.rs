@@ -0,0 +1,241 @@
+/*
+ * The shell.rs file of srap
+ *
+ * Copyright 2024 © max 74.25 <maximillian[at]disroot[dot]org>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but
+ * WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use std::path::PathBuf;
+
+// a line the user wants appended, broken into the pieces that differ across
+// shell dialects; everything that isn't an alias or an export passes through
+// untouched
+enum Statement {
+    Alias { name: String, value: String },
+    Export { var: String, value: String },
+    Plain(String),
+}
+
+// strip a single layer of matching quotes, if present
+fn unquote(value: &str) -> String {
+    let value = value.trim();
+    let quoted = value.len() >= 2
+        && ((value.starts_with('\'') && value.ends_with('\''))
+            || (value.starts_with('"') && value.ends_with('"')));
+
+    if quoted { value[1..value.len() - 1].to_owned() } else { value.to_owned() }
+}
+
+fn parse_statement(input: &str) -> Statement {
+    if let Some(rest) = input.trim_start().strip_prefix("alias ") {
+        if let Some((name, value)) = rest.split_once('=') {
+            return Statement::Alias { name: name.trim().to_owned(), value: unquote(value) };
+        }
+    }
+
+    if let Some(rest) = input.trim_start().strip_prefix("export ") {
+        if let Some((var, value)) = rest.split_once('=') {
+            return Statement::Export { var: var.trim().to_owned(), value: unquote(value) };
+        }
+    }
+
+    Statement::Plain(input.to_owned())
+}
+
+// translates a line srap is about to append into a particular shell's real
+// syntax, and knows where that shell keeps its rc file
+pub trait Shell {
+    // the rc file this shell reads on startup, rooted at `home` and (where
+    // the shell is XDG-aware) `xdg_config_home`
+    fn rc_path(&self, home: &str, xdg_config_home: Option<&str>) -> PathBuf;
+
+    // every location this shell might already keep its rc file, most
+    // preferred first; `--all` picks whichever one actually exists instead
+    // of assuming `rc_path`'s answer is the only option
+    fn rc_candidates(&self, home: &str, xdg_config_home: Option<&str>) -> Vec<PathBuf> {
+        vec![self.rc_path(home, xdg_config_home)]
+    }
+
+    fn format_alias(&self, name: &str, value: &str) -> String;
+    fn format_export(&self, var: &str, value: &str) -> String;
+
+    // rewrite `input` into this shell's dialect; plain lines are left as-is
+    fn format_line(&self, input: &str) -> String {
+        match parse_statement(input) {
+            Statement::Alias { name, value } => self.format_alias(&name, &value),
+            Statement::Export { var, value } => self.format_export(&var, &value),
+            Statement::Plain(line) => line,
+        }
+    }
+
+    // the character this shell's rc file uses to start a comment, for the
+    // managed-block markers
+    fn comment_char(&self) -> &str { "#" }
+}
+
+// bash, zsh, ksh, and nsh all share alias/export syntax; only the rc file
+// they read differs
+pub enum Posix {
+    Bash,
+    Zsh,
+    Ksh,
+    Nsh,
+}
+
+impl Shell for Posix {
+    fn rc_path(&self, home: &str, _xdg_config_home: Option<&str>) -> PathBuf {
+        let file = match self {
+            Posix::Bash => ".bashrc",
+            Posix::Zsh => ".zshrc",
+            Posix::Ksh => ".kshrc",
+            Posix::Nsh => ".nshrc",
+        };
+
+        PathBuf::from(home).join(file)
+    }
+
+    fn format_alias(&self, name: &str, value: &str) -> String { format!("alias {name}='{value}'") }
+    fn format_export(&self, var: &str, value: &str) -> String { format!("export {var}={value}") }
+}
+
+pub struct Fish;
+
+impl Shell for Fish {
+    fn rc_path(&self, home: &str, xdg_config_home: Option<&str>) -> PathBuf {
+        match xdg_config_home {
+            Some(xdg) => PathBuf::from(xdg).join("fish/config.fish"),
+            None => PathBuf::from(home).join(".config/fish/config.fish"),
+        }
+    }
+
+    fn format_alias(&self, name: &str, value: &str) -> String { format!("alias {name} '{value}'") }
+    fn format_export(&self, var: &str, value: &str) -> String { format!("set -Ux {var} {value}") }
+}
+
+pub struct Tcsh;
+
+impl Shell for Tcsh {
+    // tcsh reads ~/.tcshrc if present, falling back to the csh-compatible
+    // ~/.cshrc when neither exists yet
+    fn rc_path(&self, home: &str, xdg_config_home: Option<&str>) -> PathBuf {
+        let candidates = self.rc_candidates(home, xdg_config_home);
+        candidates.iter().find(|path| path.is_file()).cloned().unwrap_or_else(|| PathBuf::from(home).join(".cshrc"))
+    }
+
+    fn rc_candidates(&self, home: &str, _xdg_config_home: Option<&str>) -> Vec<PathBuf> {
+        vec![PathBuf::from(home).join(".tcshrc"), PathBuf::from(home).join(".cshrc")]
+    }
+
+    fn format_alias(&self, name: &str, value: &str) -> String { format!("alias {name} '{value}'") }
+    fn format_export(&self, var: &str, value: &str) -> String { format!("setenv {var} {value}") }
+}
+
+pub struct Ion;
+
+impl Shell for Ion {
+    fn rc_path(&self, home: &str, xdg_config_home: Option<&str>) -> PathBuf {
+        match xdg_config_home {
+            Some(xdg) => PathBuf::from(xdg).join("ion/initrc"),
+            None => PathBuf::from(home).join(".config/ion/initrc"),
+        }
+    }
+
+    fn format_alias(&self, name: &str, value: &str) -> String { format!("alias {name} = {value}") }
+    fn format_export(&self, var: &str, value: &str) -> String { format!("export {var} = {value}") }
+}
+
+// pick the Shell implementor whose name appears in `$SHELL`, the same
+// substring matching srap has always used
+pub fn detect(shell_env: &str) -> Option<Box<dyn Shell>> {
+    if shell_env.contains("zsh") { Some(Box::new(Posix::Zsh)) }
+    else if shell_env.contains("bash") { Some(Box::new(Posix::Bash)) }
+    else if shell_env.contains("nsh") { Some(Box::new(Posix::Nsh)) }
+    else if shell_env.contains("ksh") { Some(Box::new(Posix::Ksh)) }
+    else if shell_env.contains("fish") { Some(Box::new(Fish)) }
+    else if shell_env.contains("ion") { Some(Box::new(Ion)) }
+    else if shell_env.contains("tcsh") { Some(Box::new(Tcsh)) }
+    else { None }
+}
+
+// every shell srap knows how to write an rc file for; `--all` writes to one
+// of each instead of just the POSIX set
+pub fn all() -> Vec<Box<dyn Shell>> {
+    vec![
+        Box::new(Posix::Bash),
+        Box::new(Posix::Zsh),
+        Box::new(Posix::Nsh),
+        Box::new(Posix::Ksh),
+        Box::new(Fish),
+        Box::new(Ion),
+        Box::new(Tcsh),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn posix_formats_alias_and_export() {
+        assert_eq!(Posix::Bash.format_line("alias ll=ls -la"), "alias ll='ls -la'");
+        assert_eq!(Posix::Bash.format_line("export PATH=$HOME/bin"), "export PATH=$HOME/bin");
+    }
+
+    #[test]
+    fn fish_translates_alias_and_export_syntax() {
+        assert_eq!(Fish.format_line("alias ll='ls -la'"), "alias ll 'ls -la'");
+        assert_eq!(Fish.format_line("export FOO=bar"), "set -Ux FOO bar");
+    }
+
+    #[test]
+    fn tcsh_translates_alias_and_export_syntax() {
+        assert_eq!(Tcsh.format_line("alias ll='ls -la'"), "alias ll 'ls -la'");
+        assert_eq!(Tcsh.format_line("export FOO=bar"), "setenv FOO bar");
+    }
+
+    #[test]
+    fn ion_translates_alias_and_export_syntax() {
+        assert_eq!(Ion.format_line("alias ll='ls -la'"), "alias ll = ls -la");
+        assert_eq!(Ion.format_line("export FOO=bar"), "export FOO = bar");
+    }
+
+    #[test]
+    fn plain_lines_pass_through_unchanged() {
+        assert_eq!(Posix::Zsh.format_line("eval \"$(starship init zsh)\""), "eval \"$(starship init zsh)\"");
+    }
+
+    use crate::testutil::scratch_dir;
+
+    #[test]
+    fn tcsh_rc_path_prefers_tcshrc_when_it_exists() {
+        let dir = scratch_dir("tcshrc");
+        std::fs::write(dir.join(".tcshrc"), "").unwrap();
+
+        let home = dir.to_string_lossy().into_owned();
+        assert_eq!(Tcsh.rc_path(&home, None), dir.join(".tcshrc"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn tcsh_rc_path_falls_back_to_cshrc() {
+        let dir = scratch_dir("cshrc-fallback");
+
+        let home = dir.to_string_lossy().into_owned();
+        assert_eq!(Tcsh.rc_path(&home, None), dir.join(".cshrc"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,34 @@
+/*
+ * The testutil.rs file of srap
+ *
+ * Copyright 2024 © max 74.25 <maximillian[at]disroot[dot]org>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but
+ * WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+*/
+
+// shared test-only fixtures, so modules that need a scratch directory on
+// disk don't each grow their own copy
+
+use std::sync::atomic::{ AtomicU32, Ordering };
+
+// a fresh scratch directory per test, so parallel test runs don't trip over
+// each other's files
+pub fn scratch_dir(name: &str) -> std::path::PathBuf {
+    static COUNTER: AtomicU32 = AtomicU32::new(0);
+
+    let unique = COUNTER.fetch_add(1, Ordering::Relaxed);
+    let dir = std::env::temp_dir().join(format!("srap-test-{name}-{}-{unique}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
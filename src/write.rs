@@ -0,0 +1,143 @@
+/*
+ * The write.rs file of srap
+ *
+ * Copyright 2024 © max 74.25 <maximillian[at]disroot[dot]org>
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but
+ * WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program. If not, see <https://www.gnu.org/licenses/>.
+*/
+
+use std::{ fs, path::Path };
+
+// one rc file srap is about to rewrite, planned ahead of time so an --all
+// run can commit every file only once it knows the whole batch is safe
+pub struct PlannedWrite {
+    pub path: String,
+    pub contents: String,
+}
+
+// write `contents` to `path` by writing a temp file in the same directory
+// and renaming it into place, so a crash mid-write never leaves a
+// half-written rc file behind
+fn write_atomic(path: &str, contents: &str) -> Result<(), String> {
+    let target = Path::new(path);
+    let dir = target.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or_else(|| Path::new("."));
+    let file_name = target.file_name().and_then(|n| n.to_str()).unwrap_or("srap-rc");
+    let tmp_path = dir.join(format!(".{file_name}.srap-tmp"));
+
+    fs::write(&tmp_path, contents).map_err(|e| format!("writing {path} failed: {e}"))?;
+    fs::rename(&tmp_path, target).map_err(|e| format!("writing {path} failed: {e}"))?;
+
+    Ok(())
+}
+
+// copy `path` to a `.bak.<timestamp>` file alongside it, returning the
+// backup's path
+fn backup(path: &str, timestamp: u64) -> Result<String, String> {
+    let bak_path = format!("{path}.bak.{timestamp}");
+    fs::copy(path, &bak_path).map_err(|e| format!("backing up {path} failed: {e}"))?;
+
+    Ok(bak_path)
+}
+
+// write a single file, keeping a timestamped backup first unless
+// `keep_backup` is false
+pub fn write_file(path: &str, contents: &str, keep_backup: bool, timestamp: u64) -> Result<(), String> {
+    if keep_backup { backup(path, timestamp)?; }
+
+    write_atomic(path, contents)
+}
+
+// apply every planned write, backing each file up first. If either the
+// backup or the write itself fails partway through, every file already
+// written in this batch is restored from its backup before the error is
+// returned. With `keep_backup` false there's nothing to roll back to, so a
+// failure just leaves the earlier files in the batch written.
+pub fn write_batch(writes: &[PlannedWrite], keep_backup: bool, timestamp: u64) -> Result<(), String> {
+    let mut done: Vec<(&str, Option<String>)> = Vec::new();
+
+    for planned in writes {
+        let outcome = (if keep_backup { backup(&planned.path, timestamp).map(Some) } else { Ok(None) })
+            .and_then(|backup_path| write_atomic(&planned.path, &planned.contents).map(|_| backup_path));
+
+        match outcome {
+            Ok(backup_path) => done.push((&planned.path, backup_path)),
+            Err(e) => {
+                for (path, bak) in done.iter().rev() {
+                    if let Some(bak) = bak {
+                        let _ = fs::copy(bak, path);
+                    }
+                }
+
+                return Err(e);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::scratch_dir;
+
+    #[test]
+    fn write_batch_rolls_back_already_written_files_when_a_later_backup_fails() {
+        let dir = scratch_dir("write-batch-rollback");
+
+        let good_path = dir.join("good_rc");
+        fs::write(&good_path, "original\n").unwrap();
+
+        // a path that is a directory, not a file: fs::copy backing it up fails
+        let bad_path = dir.join("bad_rc");
+        fs::create_dir(&bad_path).unwrap();
+
+        let writes = vec![
+            PlannedWrite { path: good_path.to_string_lossy().into_owned(), contents: "new\n".to_owned() },
+            PlannedWrite { path: bad_path.to_string_lossy().into_owned(), contents: "new\n".to_owned() },
+        ];
+
+        let result = write_batch(&writes, true, 1);
+
+        assert!(result.is_err());
+        assert_eq!(fs::read_to_string(&good_path).unwrap(), "original\n");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn write_batch_leaves_earlier_files_written_when_backups_are_disabled() {
+        let dir = scratch_dir("write-batch-no-backup");
+
+        let good_path = dir.join("good_rc");
+        fs::write(&good_path, "original\n").unwrap();
+
+        // a path that is a directory, not a file: write_atomic's rename into
+        // place fails
+        let bad_path = dir.join("bad_rc");
+        fs::create_dir(&bad_path).unwrap();
+
+        let writes = vec![
+            PlannedWrite { path: good_path.to_string_lossy().into_owned(), contents: "new\n".to_owned() },
+            PlannedWrite { path: bad_path.to_string_lossy().into_owned(), contents: "new\n".to_owned() },
+        ];
+
+        let result = write_batch(&writes, false, 1);
+
+        assert!(result.is_err());
+        assert_eq!(fs::read_to_string(&good_path).unwrap(), "new\n");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}